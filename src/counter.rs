@@ -4,8 +4,8 @@ use std::fmt;
 
 #[derive(Eq, PartialEq)]
 pub struct TagCount<T: AsRef<str>> {
-    tag: T,
-    count: u32,
+    pub tag: T,
+    pub count: u32,
 }
 
 impl<T: AsRef<str>> TagCount<T> {
@@ -67,6 +67,92 @@ impl<'a> From<Counter<'a>> for Vec<TagCount<&'a str>> {
     }
 }
 
+#[derive(Eq, PartialEq)]
+pub struct TagPairCount<'a> {
+    pub tags: (&'a str, &'a str),
+    pub count: u32,
+}
+
+impl<'a> TagPairCount<'a> {
+    fn new(tags: (&'a str, &'a str), count: u32) -> Self {
+        TagPairCount { tags, count }
+    }
+}
+
+impl<'a> Ord for TagPairCount<'a> {
+    fn cmp(&self, other: &TagPairCount<'a>) -> Ordering {
+        match other.count.cmp(&self.count) {
+            Ordering::Greater => Ordering::Greater,
+            Ordering::Less => Ordering::Less,
+            Ordering::Equal => self.tags.cmp(&other.tags),
+        }
+    }
+}
+
+impl<'a> PartialOrd for TagPairCount<'a> {
+    fn partial_cmp(&self, other: &TagPairCount<'a>) -> Option<Ordering> {
+        Some(self.cmp(&other))
+    }
+}
+
+impl<'a> fmt::Display for TagPairCount<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} + {}: {}", self.tags.0, self.tags.1, self.count)
+    }
+}
+
+/// Counts how often pairs of tags appear together on the same post, to
+/// surface which tags tend to cluster.
+#[derive(Debug)]
+pub struct CooccurrenceCounter<'a> {
+    map: HashMap<(&'a str, &'a str), u32>,
+}
+
+impl<'a> CooccurrenceCounter<'a> {
+    pub fn new() -> CooccurrenceCounter<'a> {
+        CooccurrenceCounter {
+            map: HashMap::new(),
+        }
+    }
+
+    /// Record every unordered pair of tags on a single post.
+    pub fn record(&mut self, tags: &[&'a str]) {
+        // Tags can repeat on a post (e.g. multiple source tags collapsing to
+        // the same destination tag via `--tag-mappings`), so dedupe before
+        // pairing to avoid nonsensical self-pairs like `"a" + "a"`.
+        let mut unique: Vec<&'a str> = tags.to_vec();
+        unique.sort_unstable();
+        unique.dedup();
+
+        for (i, a) in unique.iter().enumerate() {
+            for b in &unique[i + 1..] {
+                let pair = canonical_pair(a, b);
+                self.map.entry(pair).and_modify(|c| *c += 1).or_insert(1);
+            }
+        }
+    }
+}
+
+impl<'a> From<CooccurrenceCounter<'a>> for Vec<TagPairCount<'a>> {
+    fn from(counter: CooccurrenceCounter<'a>) -> Self {
+        counter
+            .map
+            .into_iter()
+            .map(|(pair, count)| TagPairCount::new(pair, count))
+            .collect()
+    }
+}
+
+// Canonicalize on sorted order so `(a, b)` and `(b, a)` hash to the same
+// entry and symmetric pairs aren't double-counted.
+fn canonical_pair<'a>(a: &'a str, b: &'a str) -> (&'a str, &'a str) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,4 +183,39 @@ mod tests {
         assert_eq!(counts[0].tag, "sidecar");
         assert_eq!(counts[0].count, 1);
     }
+
+    #[test]
+    fn cooccurrence_is_order_independent() {
+        let mut counter = CooccurrenceCounter::new();
+        counter.record(&["a", "b"]);
+        counter.record(&["b", "a"]);
+
+        let counts: Vec<TagPairCount> = counter.into();
+
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[0].tags, ("a", "b"));
+        assert_eq!(counts[0].count, 2);
+    }
+
+    #[test]
+    fn cooccurrence_counts_every_pair_on_a_post() {
+        let mut counter = CooccurrenceCounter::new();
+        counter.record(&["a", "b", "c"]);
+
+        let counts: Vec<TagPairCount> = counter.into();
+
+        assert_eq!(counts.len(), 3);
+    }
+
+    #[test]
+    fn duplicate_tags_on_a_post_do_not_produce_a_self_pair() {
+        let mut counter = CooccurrenceCounter::new();
+        counter.record(&["a", "a", "b"]);
+
+        let counts: Vec<TagPairCount> = counter.into();
+
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[0].tags, ("a", "b"));
+        assert_eq!(counts[0].count, 1);
+    }
 }
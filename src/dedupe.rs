@@ -0,0 +1,191 @@
+use anyhow::{Context, Result};
+use siphasher::sip128::{Hasher128, SipHasher13};
+use std::collections::HashMap;
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// Only the first 4 KiB are hashed at the partial stage; this is enough to
+// rule out almost all non-duplicate files while staying far cheaper than
+// reading the whole file, which only happens for files that already match
+// on both size and partial hash.
+const PARTIAL_HASH_BYTES: usize = 4096;
+
+#[derive(Debug)]
+pub struct DuplicateGroup {
+    pub paths: Vec<PathBuf>,
+}
+
+/// Find byte-identical files in `file_cache` using a size -> partial hash ->
+/// full hash cascade, so that only files which already agree on size and a
+/// cheap partial hash ever need a full read.
+pub fn find_duplicates<'a>(
+    file_cache: impl IntoIterator<Item = &'a walkdir::DirEntry>,
+) -> Result<Vec<DuplicateGroup>> {
+    let entries: Vec<&walkdir::DirEntry> = file_cache.into_iter().collect();
+    let mut duplicates = Vec::new();
+
+    for (_, paths) in group_by_size(&entries) {
+        if paths.len() < 2 {
+            continue;
+        }
+
+        for (_, paths) in group_by_hash(&paths, hash_partial)? {
+            if paths.len() < 2 {
+                continue;
+            }
+
+            for (_, paths) in group_by_hash(&paths, hash_full)? {
+                if paths.len() > 1 {
+                    duplicates.push(DuplicateGroup { paths });
+                }
+            }
+        }
+    }
+
+    Ok(duplicates)
+}
+
+fn group_by_size(file_cache: &[&walkdir::DirEntry]) -> HashMap<u64, Vec<PathBuf>> {
+    let mut groups: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+
+    for entry in file_cache {
+        if let Ok(metadata) = entry.metadata() {
+            groups
+                .entry(metadata.len())
+                .or_default()
+                .push(entry.path().to_path_buf());
+        }
+    }
+
+    groups
+}
+
+fn group_by_hash(
+    paths: &[PathBuf],
+    hash_fn: impl Fn(&Path) -> Result<u128>,
+) -> Result<HashMap<u128, Vec<PathBuf>>> {
+    let mut groups: HashMap<u128, Vec<PathBuf>> = HashMap::new();
+
+    for path in paths {
+        let hash = hash_fn(path)?;
+        groups.entry(hash).or_default().push(path.clone());
+    }
+
+    Ok(groups)
+}
+
+fn hash_partial(path: &Path) -> Result<u128> {
+    let mut file =
+        File::open(path).context(format!("unable to open file {}", path.display()))?;
+    let mut buf = [0u8; PARTIAL_HASH_BYTES];
+    let n = file
+        .read(&mut buf)
+        .context(format!("unable to read file {}", path.display()))?;
+
+    let mut hasher = SipHasher13::new();
+    hasher.write(&buf[..n]);
+
+    Ok(hasher.finish128().as_u128())
+}
+
+fn hash_full(path: &Path) -> Result<u128> {
+    let mut file =
+        File::open(path).context(format!("unable to open file {}", path.display()))?;
+    let mut hasher = SipHasher13::new();
+    let mut buf = [0u8; 65536];
+
+    loop {
+        let n = file
+            .read(&mut buf)
+            .context(format!("unable to read file {}", path.display()))?;
+
+        if n == 0 {
+            break;
+        }
+
+        hasher.write(&buf[..n]);
+    }
+
+    Ok(hasher.finish128().as_u128())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::tempdir;
+    use walkdir::WalkDir;
+
+    fn entries_in(dir: &Path) -> Vec<walkdir::DirEntry> {
+        WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .collect()
+    }
+
+    fn write_file(path: &Path, contents: &[u8]) {
+        File::create(path).unwrap().write_all(contents).unwrap();
+    }
+
+    #[test]
+    fn identical_files_are_grouped_as_duplicates() {
+        let dir = tempdir().unwrap();
+        write_file(&dir.path().join("a.jpg"), b"duplicate content");
+        write_file(&dir.path().join("b.jpg"), b"duplicate content");
+
+        let entries = entries_in(dir.path());
+        let groups = find_duplicates(entries.iter()).unwrap();
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].paths.len(), 2);
+    }
+
+    #[test]
+    fn same_size_different_content_is_not_grouped() {
+        let dir = tempdir().unwrap();
+        write_file(&dir.path().join("a.jpg"), b"aaaaaaaaaa");
+        write_file(&dir.path().join("b.jpg"), b"bbbbbbbbbb");
+
+        let entries = entries_in(dir.path());
+        let groups = find_duplicates(entries.iter()).unwrap();
+
+        assert!(groups.is_empty());
+    }
+
+    #[test]
+    fn matching_partial_hash_alone_is_not_enough_to_be_a_duplicate() {
+        // Both files share an identical first PARTIAL_HASH_BYTES and are the
+        // same total size, so they reach (and collide at) the partial-hash
+        // stage; only a full hash over the entire file should tell them
+        // apart, since the tails differ.
+        let mut a = vec![0u8; PARTIAL_HASH_BYTES];
+        a.extend_from_slice(b"first tail");
+        let mut b = vec![0u8; PARTIAL_HASH_BYTES];
+        b.extend_from_slice(b"second tail");
+        b.truncate(a.len());
+
+        let dir = tempdir().unwrap();
+        write_file(&dir.path().join("a.jpg"), &a);
+        write_file(&dir.path().join("b.jpg"), &b);
+
+        let entries = entries_in(dir.path());
+        let groups = find_duplicates(entries.iter()).unwrap();
+
+        assert!(groups.is_empty());
+    }
+
+    #[test]
+    fn different_sizes_never_reach_the_hashing_stages() {
+        let dir = tempdir().unwrap();
+        write_file(&dir.path().join("a.jpg"), b"short");
+        write_file(&dir.path().join("b.jpg"), b"a bit longer");
+
+        let entries = entries_in(dir.path());
+        let groups = find_duplicates(entries.iter()).unwrap();
+
+        assert!(groups.is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+
+const JPEG_XMP_NAMESPACE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+const PNG_SIGNATURE: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
+
+pub enum EmbedOutcome {
+    Embedded,
+    Unsupported,
+}
+
+/// Embed `tags` as an XMP `dc:subject` packet directly into `path`'s own
+/// metadata, so JPEG/PNG files carry their keywords without a `.txt`
+/// sidecar. Returns `Unsupported` for formats that can't carry XMP, so the
+/// caller can fall back to writing a sidecar instead.
+pub fn embed_tags(path: &Path, tags: &[String]) -> Result<EmbedOutcome> {
+    let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+
+    match ext.as_deref() {
+        Some("jpg") | Some("jpeg") => {
+            embed_jpeg(path, tags)?;
+            Ok(EmbedOutcome::Embedded)
+        }
+        Some("png") => {
+            embed_png(path, tags)?;
+            Ok(EmbedOutcome::Embedded)
+        }
+        _ => Ok(EmbedOutcome::Unsupported),
+    }
+}
+
+fn embed_jpeg(path: &Path, tags: &[String]) -> Result<()> {
+    let data = fs::read(path).context(format!("unable to read {}", path.display()))?;
+
+    if data.len() < 2 || data[0..2] != [0xFF, 0xD8] {
+        bail!("{} is not a valid JPEG file", path.display());
+    }
+
+    let packet = xmp_packet(tags);
+    let mut segment = Vec::with_capacity(JPEG_XMP_NAMESPACE.len() + packet.len());
+    segment.extend_from_slice(JPEG_XMP_NAMESPACE);
+    segment.extend_from_slice(&packet);
+
+    if segment.len() + 2 > u16::MAX as usize {
+        bail!(
+            "{} has too many tags to embed: XMP segment would be {} bytes, exceeding the JPEG APP1 limit of {} bytes",
+            path.display(),
+            segment.len() + 2,
+            u16::MAX
+        );
+    }
+
+    let segment_len = (segment.len() + 2) as u16;
+
+    // The new APP1 segment goes immediately after the SOI marker, ahead of
+    // any existing segments.
+    let mut out = Vec::with_capacity(data.len() + segment.len() + 4);
+    out.extend_from_slice(&data[0..2]);
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&segment_len.to_be_bytes());
+    out.extend_from_slice(&segment);
+    out.extend_from_slice(&data[2..]);
+
+    fs::write(path, out).context(format!("unable to write {}", path.display()))
+}
+
+fn embed_png(path: &Path, tags: &[String]) -> Result<()> {
+    let data = fs::read(path).context(format!("unable to read {}", path.display()))?;
+
+    if data.len() < 8 || data[0..8] != PNG_SIGNATURE {
+        bail!("{} is not a valid PNG file", path.display());
+    }
+
+    // IHDR is always the first chunk and always carries 13 bytes of data,
+    // so the new iTXt chunk can be inserted right after it.
+    let ihdr_end = 8 + 4 + 4 + 13 + 4;
+
+    if data.len() < ihdr_end {
+        bail!("{} is not a valid PNG file", path.display());
+    }
+
+    let packet = xmp_packet(tags);
+    let mut chunk_data = Vec::with_capacity(PNG_XMP_KEYWORD.len() + 5 + packet.len());
+    chunk_data.extend_from_slice(PNG_XMP_KEYWORD);
+    // keyword-null, compression flag, compression method, then an empty
+    // language tag and an empty translated keyword, each null-terminated.
+    chunk_data.extend_from_slice(&[0, 0, 0, 0, 0]);
+    chunk_data.extend_from_slice(&packet);
+
+    let chunk = png_chunk(b"iTXt", &chunk_data);
+
+    let mut out = Vec::with_capacity(data.len() + chunk.len());
+    out.extend_from_slice(&data[0..ihdr_end]);
+    out.extend_from_slice(&chunk);
+    out.extend_from_slice(&data[ihdr_end..]);
+
+    fs::write(path, out).context(format!("unable to write {}", path.display()))
+}
+
+fn png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+    let mut chunk = Vec::with_capacity(4 + 4 + data.len() + 4);
+    chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    chunk.extend_from_slice(chunk_type);
+    chunk.extend_from_slice(data);
+
+    let crc = crc32fast::hash(&chunk[4..]);
+    chunk.extend_from_slice(&crc.to_be_bytes());
+
+    chunk
+}
+
+fn xmp_packet(tags: &[String]) -> Vec<u8> {
+    let subjects: String = tags
+        .iter()
+        .map(|t| format!("<rdf:li>{}</rdf:li>", escape_xml(t)))
+        .collect();
+
+    format!(
+        "<?xpacket begin=\"\u{FEFF}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\
+         <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\
+         <rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\
+         <dc:subject><rdf:Bag>{}</rdf:Bag></dc:subject>\
+         </rdf:Description></rdf:RDF></x:xmpmeta>\
+         <?xpacket end=\"w\"?>",
+        subjects
+    )
+    .into_bytes()
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn escape_xml_escapes_special_characters() {
+        assert_eq!(escape_xml("cats & dogs <3>"), "cats &amp; dogs &lt;3&gt;");
+    }
+
+    #[test]
+    fn escape_xml_leaves_plain_text_untouched() {
+        assert_eq!(escape_xml("vacation"), "vacation");
+    }
+
+    #[test]
+    fn xmp_packet_embeds_each_tag_as_an_rdf_list_item() {
+        let tags = vec!["beach".to_string(), "sunset".to_string()];
+        let packet = String::from_utf8(xmp_packet(&tags)).unwrap();
+
+        assert!(packet.contains("<rdf:li>beach</rdf:li>"));
+        assert!(packet.contains("<rdf:li>sunset</rdf:li>"));
+    }
+
+    #[test]
+    fn xmp_packet_escapes_tag_content() {
+        let tags = vec!["cats & dogs".to_string()];
+        let packet = String::from_utf8(xmp_packet(&tags)).unwrap();
+
+        assert!(packet.contains("<rdf:li>cats &amp; dogs</rdf:li>"));
+    }
+
+    #[test]
+    fn png_chunk_has_length_type_and_crc() {
+        let chunk = png_chunk(b"iTXt", b"hello");
+
+        let len = u32::from_be_bytes(chunk[0..4].try_into().unwrap());
+        assert_eq!(len, 5);
+        assert_eq!(&chunk[4..8], b"iTXt");
+        assert_eq!(&chunk[8..13], b"hello");
+
+        let crc = crc32fast::hash(&chunk[4..13]);
+        assert_eq!(&chunk[13..17], &crc.to_be_bytes()[..]);
+    }
+
+    #[test]
+    fn embed_jpeg_rejects_an_oversized_tag_list() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        fs::write(&path, [0xFFu8, 0xD8, 0xFF, 0xD9]).unwrap();
+
+        // Together the tags comfortably exceed the APP1 segment's u16 length
+        // limit once combined with the XMP packet boilerplate.
+        let tags: Vec<String> = (0..10_000).map(|i| format!("tag-{}", i)).collect();
+
+        let result = embed_jpeg(&path, &tags);
+
+        assert!(result.is_err());
+    }
+
+    // Minimal valid PNG: signature + IHDR (13-byte payload, any CRC accepted
+    // by our own reader since we don't validate it) + IEND.
+    fn minimal_png() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&PNG_SIGNATURE);
+        data.extend_from_slice(&png_chunk(b"IHDR", &[0; 13]));
+        data.extend_from_slice(&png_chunk(b"IEND", &[]));
+        data
+    }
+
+    /// Parse a PNG iTXt chunk's payload per spec: keyword\0, compression
+    /// flag, compression method, language-tag\0, translated-keyword\0, text.
+    /// Returns the decoded text, or `None` if a terminator is missing.
+    fn parse_itxt(chunk_data: &[u8]) -> Option<Vec<u8>> {
+        let keyword_end = chunk_data.iter().position(|&b| b == 0)?;
+        let rest = &chunk_data[keyword_end + 1..];
+
+        let (_flag, rest) = rest.split_first()?;
+        let (_method, rest) = rest.split_first()?;
+
+        let lang_end = rest.iter().position(|&b| b == 0)?;
+        let rest = &rest[lang_end + 1..];
+
+        let translated_end = rest.iter().position(|&b| b == 0)?;
+        let text = &rest[translated_end + 1..];
+
+        Some(text.to_vec())
+    }
+
+    #[test]
+    fn embed_png_produces_a_spec_compliant_itxt_chunk() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("photo.png");
+        fs::write(&path, minimal_png()).unwrap();
+
+        embed_png(&path, &["beach".to_string()]).unwrap();
+        let data = fs::read(&path).unwrap();
+
+        let itxt_start = data
+            .windows(4)
+            .position(|w| w == b"iTXt")
+            .expect("iTXt chunk type not found");
+        let len_start = itxt_start - 4;
+        let len = u32::from_be_bytes(data[len_start..itxt_start].try_into().unwrap()) as usize;
+        let chunk_data = &data[itxt_start + 4..itxt_start + 4 + len];
+
+        let text = parse_itxt(chunk_data).expect("iTXt chunk is not spec-compliant");
+        let text = String::from_utf8(text).unwrap();
+
+        assert!(text.contains("<rdf:li>beach</rdf:li>"));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{MediaType, Post};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Serialize)]
+struct JsonFeed<'a> {
+    version: &'static str,
+    title: &'static str,
+    items: Vec<JsonFeedItem<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonFeedItem<'a> {
+    id: &'a str,
+    url: &'a str,
+    media_type: &'static str,
+    tags: &'a [String],
+}
+
+/// Write `posts` as a JSON Feed 1.1 document to `out_file`.
+pub fn write_json_feed(posts: &[Post], out_file: &Path) -> Result<()> {
+    let feed = JsonFeed {
+        version: "https://jsonfeed.org/version/1.1",
+        title: "Tumblr export",
+        items: posts
+            .iter()
+            .map(|post| JsonFeedItem {
+                id: &post.id,
+                url: &post.url,
+                media_type: media_type_label(&post.media_type),
+                tags: &post.tags,
+            })
+            .collect(),
+    };
+
+    let file = File::create(out_file).context(format!(
+        "unable to create feed output file {}",
+        out_file.display()
+    ))?;
+
+    serde_json::to_writer_pretty(file, &feed)
+        .context(format!("unable to write feed to {}", out_file.display()))
+}
+
+/// Write `posts` as an RSS 2.0 document to `out_file`.
+pub fn write_rss_feed(posts: &[Post], out_file: &Path) -> Result<()> {
+    let mut file = File::create(out_file).context(format!(
+        "unable to create feed output file {}",
+        out_file.display()
+    ))?;
+
+    writeln!(file, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(file, "<rss version=\"2.0\"><channel>")?;
+    writeln!(file, "<title>Tumblr export</title>")?;
+
+    for post in posts {
+        writeln!(file, "<item>")?;
+        writeln!(file, "<guid>{}</guid>", escape_xml(&post.id))?;
+        writeln!(file, "<link>{}</link>", escape_xml(&post.url))?;
+        writeln!(
+            file,
+            "<category>{}</category>",
+            media_type_label(&post.media_type)
+        )?;
+
+        for tag in &post.tags {
+            writeln!(file, "<category>{}</category>", escape_xml(tag))?;
+        }
+
+        writeln!(file, "</item>")?;
+    }
+
+    writeln!(file, "</channel></rss>")?;
+
+    Ok(())
+}
+
+fn media_type_label(media_type: &MediaType) -> &'static str {
+    match media_type {
+        MediaType::Text => "text",
+        MediaType::Photo => "photo",
+        MediaType::Other => "other",
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn post(id: &str, url: &str, tags: &[&str]) -> Post {
+        Post {
+            id: id.to_string(),
+            url: url.to_string(),
+            media_type: MediaType::Photo,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn write_json_feed_includes_every_post() {
+        let dir = tempdir().unwrap();
+        let out_file = dir.path().join("feed.json");
+        let posts = vec![post("1", "https://example.com/1", &["a", "b"])];
+
+        write_json_feed(&posts, &out_file).unwrap();
+        let contents = fs::read_to_string(&out_file).unwrap();
+
+        assert!(contents.contains("\"id\": \"1\""));
+        assert!(contents.contains("\"url\": \"https://example.com/1\""));
+        assert!(contents.contains("\"a\""));
+        assert!(contents.contains("\"b\""));
+    }
+
+    #[test]
+    fn write_rss_feed_includes_every_post() {
+        let dir = tempdir().unwrap();
+        let out_file = dir.path().join("feed.rss");
+        let posts = vec![post("1", "https://example.com/1", &["vacation"])];
+
+        write_rss_feed(&posts, &out_file).unwrap();
+        let contents = fs::read_to_string(&out_file).unwrap();
+
+        assert!(contents.contains("<guid>1</guid>"));
+        assert!(contents.contains("<link>https://example.com/1</link>"));
+        assert!(contents.contains("<category>vacation</category>"));
+    }
+
+    #[test]
+    fn write_rss_feed_escapes_special_characters_in_tags() {
+        let dir = tempdir().unwrap();
+        let out_file = dir.path().join("feed.rss");
+        let posts = vec![post("1", "https://example.com/1", &["cats & dogs <3>"])];
+
+        write_rss_feed(&posts, &out_file).unwrap();
+        let contents = fs::read_to_string(&out_file).unwrap();
+
+        assert!(contents.contains("<category>cats &amp; dogs &lt;3&gt;</category>"));
+        assert!(!contents.contains("cats & dogs <3>"));
+    }
+
+    #[test]
+    fn escape_xml_escapes_all_special_characters() {
+        assert_eq!(
+            escape_xml(r#"a & b < c > d " e ' f"#),
+            "a &amp; b &lt; c &gt; d &quot; e &apos; f"
+        );
+    }
+}
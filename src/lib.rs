@@ -1,12 +1,19 @@
 use anyhow::{bail, Context, Result};
-use counter::{Counter, TagCount};
+use counter::{CooccurrenceCounter, Counter, TagCount, TagPairCount};
+use rayon::prelude::*;
 use std::collections::HashMap;
-use std::fs::{self, File};
+use std::fs::File;
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use structopt::StructOpt;
+use walkdir::WalkDir;
 
 mod counter;
+mod dedupe;
+mod embed;
+mod feed;
+mod media_info;
 mod parser;
 
 #[derive(StructOpt, Debug)]
@@ -38,14 +45,124 @@ pub enum Config {
             help = "Report media posts without corresponding files in the media directory"
         )]
         report_missing: bool,
+        #[structopt(
+            long = "embed",
+            help = "Embed tags into image metadata as XMP dc:subject (IPTC keywords are not written) instead of a .txt sidecar, where the format supports it"
+        )]
+        embed: bool,
+        #[structopt(
+            name = "max-depth",
+            long = "max-depth",
+            help = "Maximum depth to recurse into the media directory (default: unlimited)"
+        )]
+        max_depth: Option<usize>,
+        #[structopt(
+            long = "with-metadata",
+            help = "Prepend image dimensions (and EXIF capture date/camera, where available) to sidecar files"
+        )]
+        with_metadata: bool,
         #[structopt(flatten)]
         common_opts: CommonOpts,
     },
     #[structopt(name = "analyze")]
     Analyze {
+        #[structopt(
+            name = "media",
+            short = "m",
+            long = "media",
+            help = "Also report photo counts by resolution, read from this media directory"
+        )]
+        media_dir: Option<PathBuf>,
+        #[structopt(
+            name = "format",
+            long = "format",
+            default_value = "text",
+            help = "Output format: text, json, or csv"
+        )]
+        format: AnalyzeFormat,
+        #[structopt(
+            long = "co-occurrence",
+            help = "Report which tags tend to appear together instead of per-tag counts"
+        )]
+        co_occurrence: bool,
+        #[structopt(
+            name = "top",
+            long = "top",
+            default_value = "20",
+            help = "Maximum number of co-occurrence pairs to report"
+        )]
+        top: usize,
         #[structopt(flatten)]
         common_opts: CommonOpts,
     },
+    #[structopt(name = "dedupe")]
+    Dedupe {
+        #[structopt(name = "media", short = "m", long = "media", default_value = "media")]
+        media_dir: PathBuf,
+        #[structopt(
+            name = "max-depth",
+            long = "max-depth",
+            help = "Maximum depth to recurse into the media directory (default: unlimited)"
+        )]
+        max_depth: Option<usize>,
+        #[structopt(flatten)]
+        common_opts: CommonOpts,
+    },
+    #[structopt(name = "export")]
+    Export {
+        #[structopt(
+            name = "format",
+            long = "format",
+            default_value = "json",
+            help = "Feed format to export: json or rss"
+        )]
+        format: ExportFormat,
+        #[structopt(name = "out", short = "o", long = "out", default_value = "feed.json")]
+        out_file: PathBuf,
+        #[structopt(flatten)]
+        common_opts: CommonOpts,
+    },
+}
+
+#[derive(Debug)]
+pub enum ExportFormat {
+    Json,
+    Rss,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "rss" => Ok(ExportFormat::Rss),
+            _ => Err(format!("invalid format '{}', expected 'json' or 'rss'", s)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AnalyzeFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for AnalyzeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(AnalyzeFormat::Text),
+            "json" => Ok(AnalyzeFormat::Json),
+            "csv" => Ok(AnalyzeFormat::Csv),
+            _ => Err(format!(
+                "invalid format '{}', expected 'text', 'json', or 'csv'",
+                s
+            )),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -79,25 +196,67 @@ pub fn run(config: Config) -> Result<()> {
         Config::Generate {
             media_dir,
             report_missing,
+            embed,
+            max_depth,
+            with_metadata,
             common_opts,
         } => {
-            let file_cache = build_file_cache(&media_dir)?;
+            let file_cache = build_file_cache(&media_dir, max_depth)?;
             let posts = parse_posts(common_opts)?;
 
-            write_sidecar_files(&posts, &file_cache)?;
+            write_sidecar_files(&posts, &file_cache, embed, with_metadata)?;
 
             if report_missing {
                 report_missing_media(&posts, &file_cache);
             }
         }
-        Config::Analyze { common_opts } => {
+        Config::Analyze {
+            media_dir,
+            format,
+            co_occurrence,
+            top,
+            common_opts,
+        } => {
             let posts = parse_posts(common_opts)?;
-            let mut tag_counts = count_tags(&posts);
 
-            tag_counts.sort();
+            if co_occurrence {
+                let mut pairs = count_cooccurrences(&posts);
+                pairs.truncate(top);
+                print_cooccurrence_pairs(&pairs, &format)?;
+            } else {
+                let mut tag_counts = count_tags(&posts);
+                tag_counts.sort();
+                print_tag_counts(&tag_counts, &format)?;
+            }
 
-            for t in &tag_counts {
-                println!("{}", t);
+            if let Some(media_dir) = media_dir {
+                let file_cache = build_file_cache(&media_dir, None)?;
+                let resolution_counts = count_resolutions(file_cache.values().flatten())?;
+
+                print_resolution_counts(&resolution_counts, &format)?;
+            }
+        }
+        Config::Dedupe {
+            media_dir,
+            max_depth,
+            common_opts,
+        } => {
+            let file_cache = build_file_cache(&media_dir, max_depth)?;
+            let posts = parse_posts(common_opts)?;
+            let duplicate_groups = dedupe::find_duplicates(file_cache.values().flatten())?;
+
+            report_duplicate_media(&posts, &duplicate_groups);
+        }
+        Config::Export {
+            format,
+            out_file,
+            common_opts,
+        } => {
+            let posts = parse_posts(common_opts)?;
+
+            match format {
+                ExportFormat::Json => feed::write_json_feed(&posts, &out_file)?,
+                ExportFormat::Rss => feed::write_rss_feed(&posts, &out_file)?,
             }
         }
     };
@@ -119,33 +278,77 @@ fn parse_posts(common_opts: CommonOpts) -> Result<Vec<Post>> {
     parser::parse_posts(posts_file, &tag_mappings)
 }
 
-fn write_sidecar_files(posts: &[Post], file_cache: &[fs::DirEntry]) -> Result<()> {
-    for post in posts {
+fn write_sidecar_files(
+    posts: &[Post],
+    file_cache: &HashMap<String, Vec<walkdir::DirEntry>>,
+    embed: bool,
+    with_metadata: bool,
+) -> Result<()> {
+    posts.par_iter().try_for_each(|post| -> Result<()> {
+        let entries = match file_cache.get(&post.id) {
+            Some(entries) => entries,
+            None => return Ok(()),
+        };
+
         let mut tags = Vec::with_capacity(post.tags.iter().fold(0, |a, t| a + t.len() + 1));
         for tag in &post.tags {
             writeln!(&mut tags, "{}", tag)?;
         }
 
-        for entry in file_cache
-            .iter()
-            .filter(|e| {
-                e.path()
-                    .file_stem()
-                    .map_or(false, |f| f.to_string_lossy().starts_with(&post.id))
-            })
-            .collect::<Vec<&fs::DirEntry>>()
-        {
+        for entry in entries {
             let path = entry.path();
             // Only write sidecar files for source files that actually exist,
             // since the initial file cache can get out of sync.
-            if entry.path().exists() {
-                let file_path = path.to_string_lossy() + ".txt";
-                let mut tags_file = File::create(file_path.as_ref())?;
-                tags_file.write_all(&tags)?;
+            if !path.exists() {
+                continue;
             }
+
+            if embed {
+                match embed::embed_tags(path, &post.tags)? {
+                    embed::EmbedOutcome::Embedded => {
+                        println!("embedded tags into {}", path.display());
+                        continue;
+                    }
+                    embed::EmbedOutcome::Unsupported => {
+                        println!(
+                            "{} can't carry embedded keywords, writing sidecar instead",
+                            path.display()
+                        );
+                    }
+                }
+            }
+
+            let mut contents = Vec::new();
+            if with_metadata {
+                if let Some(info) = media_info::read_media_info(path)? {
+                    write_metadata_header(&mut contents, &info)?;
+                }
+            }
+            contents.extend_from_slice(&tags);
+
+            let file_path = path.to_string_lossy() + ".txt";
+            let mut tags_file = File::create(file_path.as_ref())?;
+            tags_file.write_all(&contents)?;
         }
+
+        Ok(())
+    })
+}
+
+fn write_metadata_header(buf: &mut Vec<u8>, info: &media_info::MediaInfo) -> Result<()> {
+    writeln!(buf, "# width: {}", info.width)?;
+    writeln!(buf, "# height: {}", info.height)?;
+
+    if let Some(captured_at) = &info.captured_at {
+        writeln!(buf, "# captured: {}", captured_at)?;
+    }
+
+    if let Some(camera) = &info.camera {
+        writeln!(buf, "# camera: {}", camera)?;
     }
 
+    writeln!(buf)?;
+
     Ok(())
 }
 
@@ -186,41 +389,61 @@ fn load_tag_mappings<P: AsRef<Path>>(mapping_file: P) -> Result<HashMap<String,
     Ok(mappings)
 }
 
-fn build_file_cache<P: AsRef<Path>>(media_dir: P) -> Result<Vec<fs::DirEntry>> {
-    // Build a sorted cache of media files on disk to more efficiently generate
-    // sidecar files for all files related to a given post instead of relying
-    // solely on the photoset data in `posts.xml` to determine suffixes for
-    // files in multi-photo posts. Relying only on `posts.xml` leaves out any
-    // files added to reblogs of the original post which are also included in
-    // the export and should also generate a sidecar file.
-    //
-    // Note that we do not sort this cache as (based on preliminary testing)
-    // later calls to `filter()` to search the cache for files with specific
-    // prefixes cannot take advantage of sorting. If we get more clever about
-    // cache searching this may change.
-    let files: Vec<fs::DirEntry> = fs::read_dir(&media_dir)
-        .context(format!(
-            "unable to open media directory {}",
-            media_dir.as_ref().display()
-        ))?
+fn build_file_cache<P: AsRef<Path>>(
+    media_dir: P,
+    max_depth: Option<usize>,
+) -> Result<HashMap<String, Vec<walkdir::DirEntry>>> {
+    // Build a cache of media files on disk keyed by post-id prefix, so that
+    // generating sidecar files for all files related to a given post (and
+    // any reblogs, which aren't covered by the photoset data in posts.xml)
+    // only needs a single hash lookup instead of a scan of every file on
+    // disk for every post. The walk recurses into subdirectories, since
+    // Tumblr exports don't always keep media flat under `media/`.
+    media_dir.as_ref().metadata().context(format!(
+        "unable to open media directory {}",
+        media_dir.as_ref().display()
+    ))?;
+
+    let mut cache: HashMap<String, Vec<walkdir::DirEntry>> = HashMap::new();
+
+    let mut walker = WalkDir::new(&media_dir);
+    if let Some(max_depth) = max_depth {
+        walker = walker.max_depth(max_depth);
+    }
+
+    let entries = walker
+        .into_iter()
         .filter_map(|e| e.ok())
-        .filter(|e| e.path().is_file() && e.path().extension().map_or(true, |ext| ext != "txt"))
-        .collect();
+        .filter(|e| e.path().is_file() && e.path().extension().map_or(true, |ext| ext != "txt"));
+
+    for entry in entries {
+        if let Some(prefix) = id_prefix(entry.path()) {
+            cache.entry(prefix).or_default().push(entry);
+        }
+    }
 
-    Ok(files)
+    Ok(cache)
+}
+
+// Tumblr media filenames are the numeric post id optionally followed by a
+// `_`-delimited suffix (e.g. `110999942361_500.jpg`), so the id is the
+// leading run of digits in the file stem.
+fn id_prefix(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_string_lossy();
+    let prefix: String = stem.chars().take_while(|c| c.is_ascii_digit()).collect();
+
+    if prefix.is_empty() {
+        None
+    } else {
+        Some(prefix)
+    }
 }
 
-fn report_missing_media(posts: &[Post], files: &[fs::DirEntry]) {
+fn report_missing_media(posts: &[Post], file_cache: &HashMap<String, Vec<walkdir::DirEntry>>) {
     for post in posts {
-        let mut found = false;
-        for entry in files {
-            if entry.file_name().to_string_lossy().starts_with(&post.id) {
-                found = true;
-                break;
-            }
-        }
+        let found = file_cache.contains_key(&post.id);
 
-        if found == false && post.media_type == MediaType::Photo {
+        if !found && post.media_type == MediaType::Photo {
             println!(
                 "No media file(s) found for post ID {}, download them manually from {}",
                 post.id, post.url
@@ -229,6 +452,41 @@ fn report_missing_media(posts: &[Post], files: &[fs::DirEntry]) {
     }
 }
 
+fn report_duplicate_media(posts: &[Post], groups: &[dedupe::DuplicateGroup]) {
+    let posts_by_id: HashMap<&str, &Post> =
+        posts.iter().map(|post| (post.id.as_str(), post)).collect();
+
+    for group in groups {
+        println!("Duplicate media found:");
+
+        for path in &group.paths {
+            let post_id = id_prefix(path)
+                .and_then(|prefix| posts_by_id.get(prefix.as_str()))
+                .map_or("unknown", |post| post.id.as_str());
+
+            println!("  {} (post {})", path.display(), post_id);
+        }
+    }
+}
+
+fn count_resolutions<'a>(
+    entries: impl Iterator<Item = &'a walkdir::DirEntry>,
+) -> Result<Vec<(String, u32)>> {
+    let mut counts: HashMap<String, u32> = HashMap::new();
+
+    for entry in entries {
+        if let Some(info) = media_info::read_media_info(entry.path())? {
+            let bucket = media_info::resolution_bucket(info.width, info.height);
+            *counts.entry(bucket).or_insert(0) += 1;
+        }
+    }
+
+    let mut counts: Vec<(String, u32)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(counts)
+}
+
 fn count_tags(posts: &[Post]) -> Vec<TagCount<&str>> {
     let mut counter: Counter = Default::default();
 
@@ -240,3 +498,110 @@ fn count_tags(posts: &[Post]) -> Vec<TagCount<&str>> {
 
     counter.into()
 }
+
+fn count_cooccurrences(posts: &[Post]) -> Vec<TagPairCount> {
+    let mut counter = CooccurrenceCounter::new();
+
+    for post in posts {
+        let tags: Vec<&str> = post.tags.iter().map(|t| t.as_str()).collect();
+        counter.record(&tags);
+    }
+
+    let mut pairs: Vec<TagPairCount> = counter.into();
+    pairs.sort();
+    pairs
+}
+
+fn print_tag_counts(tag_counts: &[TagCount<&str>], format: &AnalyzeFormat) -> Result<()> {
+    match format {
+        AnalyzeFormat::Text => {
+            for t in tag_counts {
+                println!("{}", t);
+            }
+        }
+        AnalyzeFormat::Json => {
+            let items: Vec<serde_json::Value> = tag_counts
+                .iter()
+                .map(|t| serde_json::json!({ "tag": t.tag, "count": t.count }))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&items)?);
+        }
+        AnalyzeFormat::Csv => {
+            println!("tag,count");
+            for t in tag_counts {
+                println!("{},{}", csv_escape(t.tag), t.count);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_cooccurrence_pairs(pairs: &[TagPairCount], format: &AnalyzeFormat) -> Result<()> {
+    match format {
+        AnalyzeFormat::Text => {
+            for p in pairs {
+                println!("{}", p);
+            }
+        }
+        AnalyzeFormat::Json => {
+            let items: Vec<serde_json::Value> = pairs
+                .iter()
+                .map(|p| serde_json::json!({ "tags": [p.tags.0, p.tags.1], "count": p.count }))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&items)?);
+        }
+        AnalyzeFormat::Csv => {
+            println!("tag_a,tag_b,count");
+            for p in pairs {
+                println!(
+                    "{},{},{}",
+                    csv_escape(p.tags.0),
+                    csv_escape(p.tags.1),
+                    p.count
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_resolution_counts(
+    resolution_counts: &[(String, u32)],
+    format: &AnalyzeFormat,
+) -> Result<()> {
+    match format {
+        AnalyzeFormat::Text => {
+            println!();
+            println!("Photos by resolution:");
+
+            for (bucket, count) in resolution_counts {
+                println!("{}: {}", bucket, count);
+            }
+        }
+        AnalyzeFormat::Json => {
+            let items: Vec<serde_json::Value> = resolution_counts
+                .iter()
+                .map(|(bucket, count)| serde_json::json!({ "resolution": bucket, "count": count }))
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&items)?);
+        }
+        AnalyzeFormat::Csv => {
+            println!("resolution,count");
+            for (bucket, count) in resolution_counts {
+                println!("{},{}", csv_escape(bucket), count);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+#[derive(Debug)]
+pub struct MediaInfo {
+    pub width: u32,
+    pub height: u32,
+    pub captured_at: Option<String>,
+    pub camera: Option<String>,
+}
+
+/// Read width/height (and, where available, EXIF capture date and camera
+/// model) for an image file. Returns `None` for files the `image` crate
+/// doesn't recognize as an image.
+pub fn read_media_info(path: &Path) -> Result<Option<MediaInfo>> {
+    let (width, height) = match image::image_dimensions(path) {
+        Ok(dimensions) => dimensions,
+        Err(_) => return Ok(None),
+    };
+
+    let (captured_at, camera) = read_exif(path).unwrap_or((None, None));
+
+    Ok(Some(MediaInfo {
+        width,
+        height,
+        captured_at,
+        camera,
+    }))
+}
+
+/// Bucket a resolution into a coarse megapixel label for aggregate stats.
+pub fn resolution_bucket(width: u32, height: u32) -> String {
+    let megapixels = (width as f64 * height as f64) / 1_000_000.0;
+
+    format!("{}MP", megapixels.round() as u64)
+}
+
+fn read_exif(path: &Path) -> Result<(Option<String>, Option<String>)> {
+    let file = File::open(path).context(format!("unable to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let exif = exif::Reader::new().read_from_container(&mut reader)?;
+
+    let captured_at = exif
+        .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
+        .map(|field| field.display_value().to_string());
+
+    let camera = exif
+        .get_field(exif::Tag::Model, exif::In::PRIMARY)
+        .map(|field| field.display_value().to_string());
+
+    Ok((captured_at, camera))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolution_bucket_keeps_an_exact_megapixel_count() {
+        // 4000 * 3000 = 12,000,000 -> 12.0MP exactly.
+        assert_eq!(resolution_bucket(4000, 3000), "12MP");
+    }
+
+    #[test]
+    fn resolution_bucket_rounds_down_when_below_the_midpoint() {
+        // 4000 * 3100 = 12,400,000 -> 12.4MP, rounds down to 12MP.
+        assert_eq!(resolution_bucket(4000, 3100), "12MP");
+    }
+
+    #[test]
+    fn resolution_bucket_rounds_up_at_the_midpoint() {
+        // 4000 * 3125 = 12,500,000 -> 12.5MP, rounds up (away from zero) to 13MP.
+        assert_eq!(resolution_bucket(4000, 3125), "13MP");
+    }
+}